@@ -1,9 +1,25 @@
+use std::cell::Cell;
 use std::time::Instant;
-use core::arch::x86_64::_rdtsc;
+#[cfg(target_arch = "x86_64")]
+use core::arch::x86_64::{__rdtscp, __cpuid};
 use criterion::measurement::Measurement;
 use criterion::measurement::ValueFormatter;
 use criterion::Throughput;
 
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+pub mod perf_event;
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+pub use perf_event::{PerfCycleCounter, PerfCycleInstant, perf_cycle_accurate_config};
+
+mod time_source;
+use time_source::{PlatformTimeSource, TimeSource};
+
+mod upkeep;
+pub use upkeep::Upkeep;
+
+mod clock;
+pub use clock::{Clock, MockClock, RealClock};
+
 /// Run function with known latency.
 /// Assume both `sub` & `and` ops are single cycle on all architectures.
 /// Might not behave as expected with another code running on the same
@@ -28,8 +44,36 @@ pub fn const_cycle_loop(mut cycles: u64) -> u64 {
 pub struct FreqInfo {
     /// Current core running frequency
     frequency: f32,
-    /// Time stamp counter scaling factor
-    tsc_scaling: f32
+    /// Time stamp counter scaling factor: core cycles per counter tick,
+    /// from the timing-loop calibration below. Deliberately *not* derived
+    /// from `nominal_tsc_hz` even where that's available, since a CPUID/OS-
+    /// reported tick rate is the counter's nominal rate, not how many core
+    /// cycles actually retire per tick once turbo boost kicks in.
+    tsc_scaling: f32,
+    /// Whether the platform counter ticks at a constant rate regardless of
+    /// CPU frequency transitions (on x86_64, CPUID leaf `0x80000007` EDX bit
+    /// 8). When `false`, `tsc_scaling`/`nominal_tsc_hz` are only a snapshot
+    /// and can drift as the core's P-state changes.
+    invariant_tsc: bool,
+    /// Nominal counter frequency in Hz read directly from hardware (CPUID
+    /// leaf `0x15`/`0x16` on x86_64, or the OS counter's own reported rate on
+    /// other platforms), when available; `None` when the platform reports no
+    /// such rate. The timing-loop calibration runs either way.
+    nominal_tsc_hz: Option<f64>
+}
+
+impl FreqInfo {
+    /// Whether the platform counter ticks at a constant rate regardless of
+    /// CPU frequency transitions. See the field doc for details.
+    pub fn invariant_tsc(&self) -> bool {
+        self.invariant_tsc
+    }
+
+    /// Nominal counter frequency in Hz read directly from hardware, when
+    /// available. See the field doc for details.
+    pub fn nominal_tsc_hz(&self) -> Option<f64> {
+        self.nominal_tsc_hz
+    }
 }
 
 /// CPU information structure. Provides frequency and TSC-to-cycle scaling information.
@@ -40,6 +84,24 @@ impl CPUInfo {
     /// Runs known latency loop and time it. This information allows to calculate core frequency.
     /// Current method might not work correctly when something running on second thread (SMT).
     pub fn get_frequency_hz() -> FreqInfo {
+        let invariant_tsc = PlatformTimeSource::invariant();
+        if !invariant_tsc {
+            static WARNED: std::sync::Once = std::sync::Once::new();
+            WARNED.call_once(|| {
+                eprintln!("performance_timing: counter is not invariant across CPU frequency \
+                            transitions; tsc_scaling/nominal frequency are only a snapshot");
+            });
+        }
+
+        // Some platforms report their counter's tick rate directly (CPUID
+        // leaves 0x15/0x16 on x86_64, QPC, CNTFRQ_EL0, the mach timebase).
+        // That's the *nominal* rate the counter ticks at, not how many core
+        // cycles actually retire per tick -- a turbo'd core still executes
+        // more than one cycle per reference tick -- so it's surfaced as
+        // `nominal_tsc_hz` only; `tsc_scaling` still needs the calibration
+        // loop below to capture the current tick-to-cycle ratio.
+        let nominal_tsc_hz = PlatformTimeSource::known_frequency_hz();
+
         let tot_cycles = 1_000_000;
         let start = Instant::now();
         // TODO: More accurate freq measurement
@@ -53,7 +115,9 @@ impl CPUInfo {
 
         return FreqInfo {
             frequency: freq,
-            tsc_scaling: tot_cycles as f32 / (ts_e - ts_s) as f32 };
+            tsc_scaling: tot_cycles as f32 / (ts_e - ts_s) as f32,
+            invariant_tsc,
+            nominal_tsc_hz };
     }
 
     /// Get core frequency in GHz.
@@ -64,14 +128,56 @@ impl CPUInfo {
         return r;
     }
 
-    /// Get current CPU time stamp counter value
-    /// Uses `RDTSC` instruction on `x86` architectures
+    /// Get the current raw counter value.
+    /// Uses `RDTSC` on x86_64, `CNTVCT_EL0` on aarch64, `QueryPerformanceCounter`
+    /// on Windows and `mach_absolute_time` on macOS; see `time_source::TimeSource`.
     pub fn get_time_stamp() -> u64 {
-        let r: u64;
+        PlatformTimeSource::now()
+    }
+
+    /// Serializing start-of-region time stamp.
+    ///
+    /// `CPUID` is a serializing instruction, so issuing it immediately before
+    /// `RDTSCP` guarantees no later instruction can execute (and no earlier one
+    /// can still be in flight) while the counter is read. This avoids the
+    /// out-of-order execution that lets a bare `_rdtsc()` absorb work from
+    /// before the start of a measured region.
+    ///
+    /// Also returns the `IA32_TSC_AUX` value (the CPU/socket id `RDTSCP`
+    /// reports) so callers can detect a thread migrating across cores mid
+    /// measurement; see `get_time_stamp_serialized_end`.
+    ///
+    /// x86_64 only: relies on `CPUID`/`RDTSCP`, which have no equivalent in
+    /// this crate on other architectures.
+    #[cfg(target_arch = "x86_64")]
+    pub fn get_time_stamp_serialized_start() -> (u64, u32) {
         unsafe {
-            r = _rdtsc();
+            __cpuid(0);
+            let mut tsc_aux: u32 = 0;
+            let tsc = __rdtscp(&mut tsc_aux as *mut u32);
+            (tsc, tsc_aux)
+        }
+    }
+
+    /// Serializing end-of-region time stamp.
+    ///
+    /// `RDTSCP` reads the counter only after every prior instruction has
+    /// retired, so the read can't float above work still executing in the
+    /// measured region. The trailing `CPUID` then stops any later instruction
+    /// from being reordered ahead of the read.
+    ///
+    /// Returns the counter value together with `IA32_TSC_AUX` (the CPU/socket
+    /// id), which callers should compare against the value from
+    /// `get_time_stamp_serialized_start`: if they differ, the thread migrated
+    /// across cores mid-measurement and the delta is meaningless.
+    #[cfg(target_arch = "x86_64")]
+    pub fn get_time_stamp_serialized_end() -> (u64, u32) {
+        unsafe {
+            let mut tsc_aux: u32 = 0;
+            let tsc = __rdtscp(&mut tsc_aux as *mut u32);
+            __cpuid(0);
+            (tsc, tsc_aux)
         }
-      return r;
     }
 }
 
@@ -88,37 +194,158 @@ impl CPUInfo {
 /// let cpu_cycles = loop_timing.get_average_sample() *
 ///   CPUInfo::get_frequency_hz().tsc_scaling;
 /// ```
-pub struct MeasureRegion {
+pub struct MeasureRegion<C: Clock = RealClock> {
     region_name: String,
     dump_on_drop: bool,
     num_samples: u64,
-    sum_samples: u64
+    sum_samples: u64,
+    samples: Vec<u64>,
+    retained_samples: Cell<u64>,
+    discarded_samples: Cell<u64>,
+    clock: C
 }
 
+/// Samples above `median + OUTLIER_K * mad()` are treated as noise (OS
+/// preemption, interrupts, SMT contention) and dropped by `get_average_sample`.
+const OUTLIER_K: f32 = 3.0;
+
 /// Measurement sample created by `MeasureRegion`
 /// Shall not be created directly.
-pub struct MeasureSample<'a> {
-    parent: &'a mut MeasureRegion,
+pub struct MeasureSample<'a, C: Clock = RealClock> {
+    parent: &'a mut MeasureRegion<C>,
     start_time: u64,
-    end_time: u64
+    end_time: u64,
+    /// `IA32_TSC_AUX` captured at `start_time` when this sample was created
+    /// via `MeasureRegion::get_sample_serialized`. `None` for the plain,
+    /// unserialized `get_sample` path.
+    start_aux: Option<u32>
 }
 
-impl MeasureRegion {
+impl MeasureRegion<RealClock> {
     pub fn new_named(region_name: String, dump_on_drop: bool) -> Self {
-        MeasureRegion { region_name, dump_on_drop, num_samples: 0, sum_samples: 0 }
+        Self::with_clock(region_name, dump_on_drop, RealClock)
     }
-    
+
     pub fn new() -> Self {
-        MeasureRegion { region_name: String::from("default_name"), dump_on_drop: false,
-                        num_samples: 0, sum_samples: 0 }
+        Self::with_clock(String::from("default_name"), false, RealClock)
+    }
+
+    /// Like `get_sample`, but uses `CPUInfo::get_time_stamp_serialized_start`/
+    /// `_end` to fence out reordering and discards the sample if the thread
+    /// migrated to a different core mid-measurement. Costs more per call than
+    /// `get_sample`, so prefer it for small regions where that overhead is
+    /// worth the added accuracy.
+    #[cfg(target_arch = "x86_64")]
+    pub fn get_sample_serialized(&mut self) -> MeasureSample<RealClock> {
+        MeasureSample::new_serialized(self)
+    }
+}
+
+impl<C: Clock> MeasureRegion<C> {
+    /// Build a region driven by an explicit `Clock` instead of the real
+    /// counter -- primarily for deterministic tests with `MockClock`.
+    pub fn with_clock(region_name: String, dump_on_drop: bool, clock: C) -> Self {
+        MeasureRegion { region_name, dump_on_drop, num_samples: 0, sum_samples: 0,
+                        samples: Vec::new(), retained_samples: Cell::new(0),
+                        discarded_samples: Cell::new(0), clock }
     }
 
-    pub fn get_sample(&mut self) -> MeasureSample {
+    pub fn get_sample(&mut self) -> MeasureSample<C> {
         MeasureSample::new(self)
     }
 
+    /// Mean of the recorded samples after dropping those above
+    /// `median + OUTLIER_K * mad()`, so a handful of OS-preemption/interrupt
+    /// spikes don't skew the result the way a plain mean would. When `mad()`
+    /// is `0` (at least half the samples share one value) no sample is
+    /// dropped. Caches the filter's outcome in
+    /// `retained_samples`/`discarded_samples`.
     pub fn get_average_sample(&self) -> f32 {
-        return self.sum_samples as f32 / self.num_samples as f32;
+        let (median, mad) = match (self.median(), self.mad()) {
+            (Some(median), Some(mad)) => (median, mad),
+            _ => {
+                self.retained_samples.set(0);
+                self.discarded_samples.set(0);
+                return self.sum_samples as f32 / self.num_samples as f32;
+            }
+        };
+
+        let threshold = median + OUTLIER_K * mad;
+        let mut kept_sum = 0u64;
+        let mut kept_count = 0u64;
+        for &sample in &self.samples {
+            if mad == 0.0f32 || sample as f32 <= threshold {
+                kept_sum += sample;
+                kept_count += 1;
+            }
+        }
+
+        self.retained_samples.set(kept_count);
+        self.discarded_samples.set(self.samples.len() as u64 - kept_count);
+
+        if kept_count == 0 {
+            return self.sum_samples as f32 / self.num_samples as f32;
+        }
+        kept_sum as f32 / kept_count as f32
+    }
+
+    /// Smallest observed sample. The least perturbed by OS scheduling,
+    /// interrupts or SMT contention, so often the best estimate of the
+    /// region's true best-case core time.
+    pub fn min(&self) -> Option<u64> {
+        self.samples.iter().copied().min()
+    }
+
+    /// Median of the recorded samples.
+    pub fn median(&self) -> Option<f32> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let mut sorted = self.samples.clone();
+        sorted.sort_unstable();
+        Some(Self::median_of_sorted_u64(&sorted))
+    }
+
+    /// Median absolute deviation of the recorded samples, scaled by `1.4826`
+    /// (the consistency factor that makes MAD a robust estimator of standard
+    /// deviation for a normal distribution).
+    pub fn mad(&self) -> Option<f32> {
+        let median = self.median()?;
+        let mut deviations: Vec<f32> = self.samples.iter()
+            .map(|&sample| (sample as f32 - median).abs())
+            .collect();
+        deviations.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        Some(Self::median_of_sorted_f32(&deviations) * 1.4826f32)
+    }
+
+    /// Samples kept by the last `get_average_sample` outlier filter.
+    pub fn retained_samples(&self) -> u64 {
+        self.retained_samples.get()
+    }
+
+    /// Samples dropped by the last `get_average_sample` outlier filter for
+    /// sitting above `median + OUTLIER_K * mad()` -- a measure of how noisy
+    /// the environment was.
+    pub fn discarded_samples(&self) -> u64 {
+        self.discarded_samples.get()
+    }
+
+    fn median_of_sorted_u64(sorted: &[u64]) -> f32 {
+        let mid = sorted.len() / 2;
+        if sorted.len() % 2 == 0 {
+            (sorted[mid - 1] + sorted[mid]) as f32 / 2.0f32
+        } else {
+            sorted[mid] as f32
+        }
+    }
+
+    fn median_of_sorted_f32(sorted: &[f32]) -> f32 {
+        let mid = sorted.len() / 2;
+        if sorted.len() % 2 == 0 {
+            (sorted[mid - 1] + sorted[mid]) / 2.0f32
+        } else {
+            sorted[mid]
+        }
     }
 
     /// Get total running time in milliseconds
@@ -129,10 +356,11 @@ impl MeasureRegion {
     fn record_sample(&mut self, sample: u64) {
         self.num_samples += 1;
         self.sum_samples += sample;
+        self.samples.push(sample);
     }
 }
 
-impl Drop for MeasureRegion {
+impl<C: Clock> Drop for MeasureRegion<C> {
     fn drop(&mut self) {
         if self.dump_on_drop {
             println!("{}: {} ref.cycles", self.region_name, self.get_average_sample());
@@ -140,10 +368,11 @@ impl Drop for MeasureRegion {
     }
 }
 
-impl<'a> MeasureSample<'a> {
+impl<'a, C: Clock> MeasureSample<'a, C> {
 
-  pub fn new(parent: &'a mut MeasureRegion) -> Self {
-    MeasureSample { parent, start_time: CPUInfo::get_time_stamp(), end_time: 0 }
+  pub fn new(parent: &'a mut MeasureRegion<C>) -> Self {
+    let start_time = parent.clock.now();
+    MeasureSample { parent, start_time, end_time: 0, start_aux: None }
   }
 
   /// Get sample value
@@ -152,56 +381,223 @@ impl<'a> MeasureSample<'a> {
   }
 }
 
-impl<'a> Drop for MeasureSample<'a> {
+impl<'a> MeasureSample<'a, RealClock> {
+  #[cfg(target_arch = "x86_64")]
+  pub fn new_serialized(parent: &'a mut MeasureRegion<RealClock>) -> Self {
+    let (start_time, start_aux) = CPUInfo::get_time_stamp_serialized_start();
+    MeasureSample { parent, start_time, end_time: 0, start_aux: Some(start_aux) }
+  }
+}
+
+#[cfg(target_arch = "x86_64")]
+impl<'a, C: Clock> Drop for MeasureSample<'a, C> {
+    fn drop(&mut self) {
+        match self.start_aux {
+            Some(start_aux) => {
+                // Serialized sample: re-fence on the way out and discard if
+                // the thread migrated across cores mid-measurement, since the
+                // cycle delta would no longer be meaningful. Only reachable
+                // via `new_serialized`, which requires `RealClock`.
+                let (end_time, end_aux) = CPUInfo::get_time_stamp_serialized_end();
+                self.end_time = end_time;
+                if end_aux == start_aux {
+                    self.parent.record_sample(self.get_value());
+                }
+            }
+            None => {
+                if self.end_time == 0 {
+                    self.end_time = self.parent.clock.now();
+                }
+                self.parent.record_sample(self.get_value());
+            }
+        }
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+impl<'a, C: Clock> Drop for MeasureSample<'a, C> {
     fn drop(&mut self) {
-        // Store sample in the parent container
         if self.end_time == 0 {
-            self.end_time = CPUInfo::get_time_stamp();
+            self.end_time = self.parent.clock.now();
         }
         self.parent.record_sample(self.get_value());
     }
 }
 
-// Get function running time in reference cycles
-pub fn measure_function_perf<F>(f: F)  -> f32
+/// Least-squares fit of `measure_function_perf`'s (iterations, cycles)
+/// samples to the line `cycles = slope * iterations + intercept`.
+#[derive(Debug)]
+pub struct PerfRegression {
+    slope: f32,
+    intercept: f32,
+    r_squared: f32
+}
+
+impl PerfRegression {
+    /// Estimated per-call cost in cycles -- the fitted line's slope, with the
+    /// fixed cost of `MeasureRegion::get_sample` cancelled out.
+    pub fn slope(&self) -> f32 {
+        self.slope
+    }
+
+    /// Estimated fixed overhead of `get_sample()` (timestamp reads plus the
+    /// loop branch) in cycles -- the fitted line's intercept.
+    pub fn intercept(&self) -> f32 {
+        self.intercept
+    }
+
+    /// Coefficient of determination of the fit, in `[0, 1]`. Closer to `1`
+    /// means the batches scaled linearly with iteration count and `slope`
+    /// can be trusted as the per-call cost.
+    pub fn r_squared(&self) -> f32 {
+        self.r_squared
+    }
+}
+
+/// Measure a function's running time in reference cycles.
+///
+/// A single fixed-size batch divides total time by iteration count, so the
+/// fixed cost of `get_sample()` itself (timestamp reads plus the loop branch)
+/// gets charged against the measured function and inflates results for cheap
+/// closures. Instead, run `f` in batches of geometrically increasing size (1,
+/// 2, 4, ...) and fit a least-squares line `cycles = slope * iterations +
+/// intercept` to the (iterations, cycles) pairs: `slope` is then the
+/// per-call cost with the constant measurement overhead cancelled out.
+pub fn measure_function_perf<F>(f: F) -> PerfRegression
 where F: Fn() {
-    let min_test: usize = 100;
     let min_bench_time: u64 = 10_000_000;
+    let max_batch: usize = 1 << 14;
+
+    let mut points: Vec<(f64, f64)> = Vec::new();
+    let mut total_time: u64 = 0;
+
+    while total_time < min_bench_time {
+        let mut batch_size: usize = 1;
+        while batch_size <= max_batch {
+            let mut m = MeasureRegion::new();
+            {
+                let _s = m.get_sample();
+                for _ in 0..batch_size {
+                    f();
+                }
+            }
+            let cycles = m.get_total_time();
+            points.push((batch_size as f64, cycles as f64));
+            total_time += cycles;
+            batch_size *= 2;
+        }
+    }
 
-    let mut m = MeasureRegion::new();
+    fit_regression(&points)
+}
 
-    while m.get_total_time() < min_bench_time {
-        let _s = m.get_sample();
-        for _ in 0..min_test {
-            f();
-        }
+/// Ordinary least-squares fit of `points` (iterations, cycles) to
+/// `cycles = slope * iterations + intercept`.
+fn fit_regression(points: &[(f64, f64)]) -> PerfRegression {
+    let n = points.len() as f64;
+    let mean_x: f64 = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y: f64 = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+
+    let cov_xy: f64 = points.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+    let var_x: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+
+    let slope = cov_xy / var_x;
+    let intercept = mean_y - slope * mean_x;
+
+    let ss_tot: f64 = points.iter().map(|(_, y)| (y - mean_y).powi(2)).sum();
+    let ss_res: f64 = points.iter()
+        .map(|(x, y)| (y - (slope * x + intercept)).powi(2))
+        .sum();
+    let r_squared = if ss_tot > 0.0 { 1.0 - ss_res / ss_tot } else { 1.0 };
+
+    PerfRegression {
+        slope: slope as f32,
+        intercept: intercept as f32,
+        r_squared: r_squared as f32
     }
-    return m.get_average_sample() / min_test as f32;
 }
 
-pub struct CycleInstant {
-    start: u64
+pub struct CycleInstant<C: Clock = RealClock> {
+    start: u64,
+    /// `IA32_TSC_AUX` captured at `start` by `now_serialized`, used by
+    /// `elapsed_serialized` to detect a core migration. `None` when this
+    /// instant was created by the plain, unserialized `now`.
+    start_aux: Option<u32>,
+    clock: C
 }
 
-impl CycleInstant {
-    pub fn now() -> CycleInstant {
-        CycleInstant { start: CPUInfo::get_time_stamp() }
+impl CycleInstant<RealClock> {
+    pub fn now() -> CycleInstant<RealClock> {
+        CycleInstant { start: CPUInfo::get_time_stamp(), start_aux: None, clock: RealClock }
+    }
+
+    /// Like `now`, but fences the read with `CPUID` so no surrounding
+    /// instruction can reorder across it. Higher overhead than `now`; use it
+    /// for short regions where that accuracy is worth the cost.
+    #[cfg(target_arch = "x86_64")]
+    pub fn now_serialized() -> CycleInstant<RealClock> {
+        let (start, start_aux) = CPUInfo::get_time_stamp_serialized_start();
+        CycleInstant { start, start_aux: Some(start_aux), clock: RealClock }
+    }
+
+    /// Like `now`, but reads the cached value a running `Upkeep` thread last
+    /// wrote instead of taking a fresh reading: a single relaxed atomic
+    /// load, with no serialization and no counter access at all. Staleness
+    /// is bounded by the `Upkeep`'s interval while one is running; `0` if no
+    /// `Upkeep` has been started yet, and the last value written once it is
+    /// dropped.
+    pub fn recent() -> CycleInstant<RealClock> {
+        CycleInstant { start: upkeep::recent_time_stamp(), start_aux: None, clock: RealClock }
+    }
+
+    /// Like `elapsed`, but reads the end time stamp the same cheap way
+    /// `recent` reads the start: the cached value a running `Upkeep` thread
+    /// last wrote, with no counter access at all. Pairs with an instant
+    /// created via `recent` -- using it with a `now`/`now_serialized`
+    /// instant would subtract a fresh start from a stale, bounded-by-the-
+    /// upkeep-interval end and produce a meaningless delta.
+    pub fn elapsed_recent(&self) -> u64 {
+        upkeep::recent_time_stamp() - self.start
+    }
+
+    /// Like `elapsed`, but reads the end time stamp with `RDTSCP` + `CPUID`
+    /// fencing. Returns `None` if the thread migrated to a different core
+    /// since `now_serialized`, since the cycle delta would be meaningless.
+    /// Only valid when `self` was created via `now_serialized`; otherwise
+    /// behaves like `elapsed` wrapped in `Some`.
+    #[cfg(target_arch = "x86_64")]
+    pub fn elapsed_serialized(&self) -> Option<u64> {
+        let (end, end_aux) = CPUInfo::get_time_stamp_serialized_end();
+        match self.start_aux {
+            Some(start_aux) if start_aux != end_aux => None,
+            _ => Some(end - self.start)
+        }
+    }
+}
+
+impl<C: Clock> CycleInstant<C> {
+    /// Create an instant driven by an explicit `Clock` instead of the real
+    /// counter -- primarily for deterministic tests with `MockClock`.
+    pub fn with_clock(clock: C) -> CycleInstant<C> {
+        CycleInstant { start: clock.now(), start_aux: None, clock }
     }
 
     pub fn elapsed(&self) -> u64 {
-        CPUInfo::get_time_stamp() - self.start
+        self.clock.now() - self.start
     }
 }
 
-/// Custom cycle accurate measurement class for criterion
-/// 
+/// Custom cycle accurate measurement class for criterion. Generic over the
+/// `Clock` used to read `CycleInstant`s, defaulting to `RealClock`; pass a
+/// `MockClock` via `with_clock` for deterministic benchmarking tests.
+///
 /// ```rust
 /// pub fn criterion_benchmark(c: &mut Criterion<CriterionCycleCounter>) {
 ///   c.bench_function("cycle_10K", |b| b.iter(|| const_cycle_loop(black_box(10_000))));
 /// }
 ///
 /// fn core_cycle_measurement() -> Criterion<CriterionCycleCounter> {
-///   Criterion::default().with_measurement(CriterionCycleCounter)
+///   Criterion::default().with_measurement(CriterionCycleCounter::default())
 /// }
 ///
 /// criterion_group! {
@@ -210,14 +606,25 @@ impl CycleInstant {
 ///   targets = criterion_benchmark
 /// }
 /// ```
-pub struct CriterionCycleCounter;
+#[derive(Default)]
+pub struct CriterionCycleCounter<C: Clock + Clone = RealClock> {
+    clock: C
+}
 
-impl Measurement for CriterionCycleCounter {
-    type Intermediate = CycleInstant;
+impl<C: Clock + Clone> CriterionCycleCounter<C> {
+    /// Build a counter driven by an explicit `Clock` instead of the real
+    /// counter -- primarily for deterministic tests with `MockClock`.
+    pub fn with_clock(clock: C) -> Self {
+        CriterionCycleCounter { clock }
+    }
+}
+
+impl<C: Clock + Clone> Measurement for CriterionCycleCounter<C> {
+    type Intermediate = CycleInstant<C>;
     type Value = u64;
 
     fn start(&self) -> Self::Intermediate {
-        CycleInstant::now()
+        CycleInstant::with_clock(self.clock.clone())
     }
 
     fn end(&self, i: Self::Intermediate) -> Self::Value {
@@ -237,11 +644,11 @@ impl Measurement for CriterionCycleCounter {
     }
 
     fn formatter(&self) -> &dyn ValueFormatter {
-        &CriterionCycleCounter
+        self
     }
 }
 
-impl ValueFormatter for CriterionCycleCounter {
+impl<C: Clock + Clone> ValueFormatter for CriterionCycleCounter<C> {
     fn format_value(&self, value: f64) -> String {
         format!("{:.3} clocks", value)
     }
@@ -280,12 +687,12 @@ impl ValueFormatter for CriterionCycleCounter {
 }
 
 pub fn cycle_accurate_config() -> criterion::Criterion<CriterionCycleCounter> {
-    criterion::Criterion::default().with_measurement(CriterionCycleCounter)
+    criterion::Criterion::default().with_measurement(CriterionCycleCounter::default())
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{CPUInfo, MeasureRegion};
+    use crate::{CPUInfo, CycleInstant, MeasureRegion, MockClock};
     use crate::const_cycle_loop;
 
     #[test]
@@ -324,4 +731,26 @@ mod tests {
         let accuracy = 0.05f32;
         assert_eq!(d < (ckl_cnt as f32 * accuracy), true);
     }
+
+    #[test]
+    fn mock_clock_measure_region_average() {
+        let clock = MockClock::new(0);
+        let mut region = MeasureRegion::with_clock(String::from("mock"), false, clock.clone());
+
+        for cycles in [10u64, 20, 30] {
+            let _s = region.get_sample();
+            clock.advance(cycles);
+        }
+
+        assert_eq!(region.get_total_time(), 60);
+        assert_eq!(region.get_average_sample(), 20.0f32);
+    }
+
+    #[test]
+    fn mock_clock_cycle_instant_elapsed() {
+        let clock = MockClock::new(100);
+        let instant = CycleInstant::with_clock(clock.clone());
+        clock.advance(42);
+        assert_eq!(instant.elapsed(), 42);
+    }
 }
@@ -0,0 +1,340 @@
+//! Hardware cycle counter backend built on Linux `perf_event_open` + `rdpmc`.
+//!
+//! `CPUInfo::get_time_stamp` reads the TSC, which only counts *reference*
+//! (wall) cycles and drifts relative to instructions actually retired once
+//! turbo boost or SMT shifts the core frequency. This module instead opens a
+//! `PERF_COUNT_HW_CPU_CYCLES` counter through the kernel `perf_event`
+//! subsystem and reads it directly from user space with the `rdpmc`
+//! instruction, giving true core-cycle counts immune to frequency scaling.
+
+use core::arch::asm;
+use std::cell::RefCell;
+use std::io;
+use std::mem;
+use std::os::raw::{c_int, c_void};
+use std::ptr;
+use std::sync::atomic::{fence, Ordering};
+
+use criterion::measurement::Measurement;
+use criterion::measurement::ValueFormatter;
+use criterion::Throughput;
+
+use crate::CPUInfo;
+
+const PERF_TYPE_HARDWARE: u32 = 0;
+const PERF_COUNT_HW_CPU_CYCLES: u64 = 0;
+const PERF_FLAG_EXCLUDE_KERNEL_BIT: u64 = 1 << 5;
+const SYS_PERF_EVENT_OPEN: i64 = 298; // x86_64 syscall number
+
+/// Mirrors the kernel's `struct perf_event_attr`, trimmed to its leading
+/// fields up to `__reserved_2`; this backend sets only `type_`, `size`,
+/// `config` and `flags` and leaves the rest zeroed. Layout and field order must match
+/// `linux/perf_event.h` exactly since it's passed straight into the syscall.
+#[repr(C)]
+struct PerfEventAttr {
+    type_: u32,
+    size: u32,
+    config: u64,
+    sample_period_or_freq: u64,
+    sample_type: u64,
+    read_format: u64,
+    flags: u64,
+    wakeup_events_or_watermark: u32,
+    bp_type: u32,
+    config1_or_bp_addr: u64,
+    config2_or_bp_len: u64,
+    branch_sample_type: u64,
+    sample_regs_user: u64,
+    sample_stack_user: u32,
+    clockid: i32,
+    sample_regs_intr: u64,
+    aux_watermark: u32,
+    sample_max_stack: u16,
+    __reserved_2: u16,
+}
+
+/// Mirrors the head of the kernel's `struct perf_event_mmap_page`, i.e. just
+/// the fields needed to drive `rdpmc` from user space. The kernel pads the
+/// real struct out to a full page; we never touch anything past `capabilities`.
+#[repr(C)]
+struct PerfEventMmapPage {
+    version: u32,
+    compat_version: u32,
+    lock: u32,
+    index: u32,
+    offset: i64,
+    time_enabled: u64,
+    time_running: u64,
+    capabilities: u64,
+}
+
+const CAP_USER_RDPMC_BIT: u64 = 1 << 2;
+
+/// `rdpmc` is not exposed as a stable intrinsic (`core::arch::x86_64::_rdpmc`
+/// doesn't exist); emit it directly. Takes the counter index in `ecx` and
+/// returns the 64-bit count split across `edx:eax`.
+unsafe fn rdpmc(counter_index: i32) -> u64 {
+    let eax: u32;
+    let edx: u32;
+    asm!(
+        "rdpmc",
+        in("ecx") counter_index,
+        out("eax") eax,
+        out("edx") edx,
+        options(nostack, nomem),
+    );
+    ((edx as u64) << 32) | eax as u64
+}
+
+/// An open `perf_event` fd with its user page mmap'd, ready for `rdpmc` reads.
+struct RdpmcMapping {
+    fd: c_int,
+    page: *mut PerfEventMmapPage,
+    page_size: usize,
+}
+
+impl RdpmcMapping {
+    fn open() -> io::Result<Self> {
+        let mut attr: PerfEventAttr = unsafe { mem::zeroed() };
+        attr.type_ = PERF_TYPE_HARDWARE;
+        attr.size = mem::size_of::<PerfEventAttr>() as u32;
+        attr.config = PERF_COUNT_HW_CPU_CYCLES;
+        attr.flags = PERF_FLAG_EXCLUDE_KERNEL_BIT; // disabled = 0, exclude_kernel = 1
+
+        let fd = unsafe {
+            libc::syscall(
+                SYS_PERF_EVENT_OPEN,
+                &attr as *const PerfEventAttr,
+                0 as libc::pid_t,  // this thread
+                -1 as c_int,       // any CPU the thread runs on
+                -1 as c_int,       // no group leader
+                0 as libc::c_ulong,
+            )
+        } as c_int;
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+        let addr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                page_size,
+                libc::PROT_READ,
+                libc::MAP_SHARED,
+                fd,
+                0,
+            )
+        };
+        if addr == libc::MAP_FAILED {
+            let err = io::Error::last_os_error();
+            unsafe { libc::close(fd) };
+            return Err(err);
+        }
+
+        Ok(RdpmcMapping { fd, page: addr as *mut PerfEventMmapPage, page_size })
+    }
+
+    /// Read the current counter value. Returns `None` if the kernel never
+    /// granted user-space `rdpmc` access (e.g. disabled by sysctl) or the
+    /// counter is transiently off the PMU under multiplexing -- callers
+    /// should fall back to the TSC themselves rather than have a TSC value
+    /// spliced into an otherwise rdpmc-based series, which would make the
+    /// delta between two endpoints meaningless.
+    fn read_cycles(&self) -> Option<u64> {
+        // perf_event_mmap_page is a seqlock: retry while `lock` is odd
+        // (an update is in progress) or changes between our reads. Also
+        // retry (bounded) on `index == 0`, which means the counter is
+        // currently descheduled from the PMU under multiplexing -- that's
+        // transient, so a short retry usually finds it back in place.
+        for _ in 0..100 {
+            let seq = unsafe { ptr::read_volatile(&(*self.page).lock) };
+            fence(Ordering::Acquire);
+            let index = unsafe { ptr::read_volatile(&(*self.page).index) };
+            let offset = unsafe { ptr::read_volatile(&(*self.page).offset) };
+            let caps = unsafe { ptr::read_volatile(&(*self.page).capabilities) };
+            fence(Ordering::Acquire);
+            let seq_end = unsafe { ptr::read_volatile(&(*self.page).lock) };
+
+            if seq_end != seq || seq % 2 != 0 {
+                continue;
+            }
+
+            if caps & CAP_USER_RDPMC_BIT == 0 {
+                return None;
+            }
+
+            if index == 0 {
+                continue;
+            }
+
+            let counter_index = (index - 1) as i32;
+            let count = unsafe { rdpmc(counter_index) } as i64 + offset;
+            return Some(count as u64);
+        }
+        None
+    }
+}
+
+impl Drop for RdpmcMapping {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.page as *mut c_void, self.page_size);
+            libc::close(self.fd);
+        }
+    }
+}
+
+enum Backend {
+    Rdpmc(RdpmcMapping),
+    /// `perf_event_open` failed (containers, `perf_event_paranoid`, ...); fall
+    /// back to the plain RDTSC path so callers keep working.
+    Fallback,
+}
+
+thread_local! {
+    static BACKEND: RefCell<Option<Backend>> = RefCell::new(None);
+}
+
+/// A single counter reading tagged with the clock it came from, so two
+/// readings from different clocks are never subtracted into a meaningless
+/// delta.
+#[derive(Clone, Copy)]
+enum CycleReading {
+    Rdpmc(u64),
+    /// `perf_event_open`/mmap failed at startup, or the thread's rdpmc
+    /// mapping transiently lost the counter to PMU multiplexing; a plain TSC
+    /// reading (reference cycles, not retired core cycles).
+    Fallback(u64),
+}
+
+fn read_cycles() -> CycleReading {
+    BACKEND.with(|cell| {
+        let mut backend = cell.borrow_mut();
+        if backend.is_none() {
+            *backend = Some(match RdpmcMapping::open() {
+                Ok(mapping) => Backend::Rdpmc(mapping),
+                Err(_) => Backend::Fallback,
+            });
+        }
+        match backend.as_ref().unwrap() {
+            Backend::Rdpmc(mapping) => match mapping.read_cycles() {
+                Some(value) => CycleReading::Rdpmc(value),
+                None => CycleReading::Fallback(CPUInfo::get_time_stamp()),
+            },
+            Backend::Fallback => CycleReading::Fallback(CPUInfo::get_time_stamp()),
+        }
+    })
+}
+
+/// `perf_event`/`rdpmc`-backed counterpart to `CycleInstant`.
+pub struct PerfCycleInstant {
+    start: CycleReading
+}
+
+impl PerfCycleInstant {
+    pub fn now() -> PerfCycleInstant {
+        PerfCycleInstant { start: read_cycles() }
+    }
+
+    /// Elapsed core cycles since `now`. The end reading normally comes from
+    /// the same clock as the start; if the thread's rdpmc mapping transiently
+    /// lost the counter to PMU multiplexing between the two reads, subtracting
+    /// a TSC end from an rdpmc start (or vice versa) would be meaningless, so
+    /// that case is reported once and the sample is discarded (reported as
+    /// `0`) instead of silently mixing clocks.
+    pub fn elapsed(&self) -> u64 {
+        match (self.start, read_cycles()) {
+            (CycleReading::Rdpmc(start), CycleReading::Rdpmc(end)) => end - start,
+            (CycleReading::Fallback(start), CycleReading::Fallback(end)) => end - start,
+            _ => {
+                static WARNED: std::sync::Once = std::sync::Once::new();
+                WARNED.call_once(|| {
+                    eprintln!("performance_timing: perf_event rdpmc mapping changed clocks \
+                                mid-measurement; discarding a sample rather than mixing \
+                                rdpmc and TSC readings");
+                });
+                0
+            }
+        }
+    }
+}
+
+/// Custom cycle accurate measurement class for criterion, backed by the
+/// kernel `perf_event` hardware cycle counter instead of the TSC. Parallel to
+/// `CriterionCycleCounter`; prefer this one when `rdpmc` access is available,
+/// since it reports retired core cycles rather than reference cycles.
+pub struct PerfCycleCounter;
+
+impl Measurement for PerfCycleCounter {
+    type Intermediate = PerfCycleInstant;
+    type Value = u64;
+
+    fn start(&self) -> Self::Intermediate {
+        PerfCycleInstant::now()
+    }
+
+    fn end(&self, i: Self::Intermediate) -> Self::Value {
+        i.elapsed()
+    }
+
+    fn add(&self, v1: &Self::Value, v2: &Self::Value) -> Self::Value {
+        *v1 + *v2
+    }
+
+    fn zero(&self) -> Self::Value {
+        0u64
+    }
+
+    fn to_f64(&self, val: &Self::Value) -> f64 {
+        // Already core cycles; unlike CriterionCycleCounter there's no
+        // TSC-to-cycle scaling to apply.
+        *val as f64
+    }
+
+    fn formatter(&self) -> &dyn ValueFormatter {
+        &PerfCycleCounter
+    }
+}
+
+impl ValueFormatter for PerfCycleCounter {
+    fn format_value(&self, value: f64) -> String {
+        format!("{:.3} core cycles", value)
+    }
+
+    fn format_throughput(&self, throughput: &Throughput, value: f64) -> String {
+        match *throughput {
+            Throughput::Bytes(bytes) => format!(
+                "{} b/c",
+                bytes as f64 / (value)
+            ),
+            Throughput::Elements(elems) => format!(
+                "{} elem/c",
+                elems as f64 / (value)
+            ),
+        }
+    }
+
+    fn scale_values(&self, _typical_value: f64, _values: &mut [f64]) -> &'static str {
+        "core cycles"
+    }
+
+    fn scale_throughputs(&self, _typical_value: f64, throughput: &Throughput, _values: &mut [f64]) -> &'static str {
+        match *throughput {
+            Throughput::Bytes(_bytes) => {
+                "b/c"
+            }
+            Throughput::Elements(_elems) => {
+                "elem/c"
+            }
+        }
+    }
+
+    fn scale_for_machines(&self, _values: &mut [f64]) -> &'static str {
+        "core cycles"
+    }
+}
+
+pub fn perf_cycle_accurate_config() -> criterion::Criterion<PerfCycleCounter> {
+    criterion::Criterion::default().with_measurement(PerfCycleCounter)
+}
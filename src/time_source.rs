@@ -0,0 +1,186 @@
+//! Cross-platform raw counter selection.
+//!
+//! `CPUInfo::get_time_stamp` used to hardwire the x86_64 `RDTSC` intrinsic, so
+//! the crate wouldn't compile anywhere else and ignored more robust OS
+//! counters where they exist. `TimeSource` picks a counter appropriate for
+//! the compile target; `CPUInfo::get_time_stamp` routes through it, so
+//! `CycleInstant`, `MeasureSample` and `CriterionCycleCounter` all follow
+//! automatically.
+
+/// A platform raw counter source.
+pub trait TimeSource {
+    /// Read the current counter value.
+    fn now() -> u64;
+
+    /// The counter's tick frequency in Hz, when the platform reports it
+    /// directly. `CPUInfo::get_frequency_hz` reports it as
+    /// `FreqInfo::nominal_tsc_hz`; it does not replace that function's
+    /// timing-loop calibration, which always runs and alone sets
+    /// `tsc_scaling`. `None` means the platform reports no such rate.
+    fn known_frequency_hz() -> Option<f64>;
+
+    /// Whether the counter's rate is guaranteed stable regardless of CPU
+    /// P-state/frequency transitions, so a `known_frequency_hz()` reading
+    /// (or a one-off calibration) stays valid for the life of the process.
+    /// True for every OS-provided counter here; on x86_64 the raw TSC needs
+    /// an explicit CPUID check since older parts let it drift with frequency.
+    fn invariant() -> bool {
+        true
+    }
+}
+
+#[cfg(target_os = "windows")]
+pub use windows::Windows as PlatformTimeSource;
+#[cfg(target_os = "windows")]
+mod windows {
+    use super::TimeSource;
+
+    extern "system" {
+        fn QueryPerformanceCounter(count: *mut i64) -> i32;
+        fn QueryPerformanceFrequency(freq: *mut i64) -> i32;
+    }
+
+    pub struct Windows;
+
+    impl TimeSource for Windows {
+        fn now() -> u64 {
+            let mut count: i64 = 0;
+            unsafe { QueryPerformanceCounter(&mut count) };
+            count as u64
+        }
+
+        fn known_frequency_hz() -> Option<f64> {
+            let mut freq: i64 = 0;
+            let ok = unsafe { QueryPerformanceFrequency(&mut freq) };
+            if ok != 0 && freq > 0 { Some(freq as f64) } else { None }
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub use macos::MacOs as PlatformTimeSource;
+#[cfg(target_os = "macos")]
+mod macos {
+    use super::TimeSource;
+
+    #[repr(C)]
+    struct MachTimebaseInfo {
+        numer: u32,
+        denom: u32
+    }
+
+    extern "C" {
+        fn mach_absolute_time() -> u64;
+        fn mach_timebase_info(info: *mut MachTimebaseInfo) -> i32;
+    }
+
+    pub struct MacOs;
+
+    impl TimeSource for MacOs {
+        fn now() -> u64 {
+            unsafe { mach_absolute_time() }
+        }
+
+        fn known_frequency_hz() -> Option<f64> {
+            let mut info = MachTimebaseInfo { numer: 0, denom: 0 };
+            let ok = unsafe { mach_timebase_info(&mut info) };
+            if ok == 0 && info.numer > 0 {
+                // mach_absolute_time() * numer / denom = elapsed nanoseconds,
+                // so the tick frequency is 1e9 * denom / numer.
+                Some(1e9 * info.denom as f64 / info.numer as f64)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+#[cfg(all(target_arch = "aarch64", not(any(target_os = "windows", target_os = "macos"))))]
+pub use aarch64::Aarch64 as PlatformTimeSource;
+#[cfg(all(target_arch = "aarch64", not(any(target_os = "windows", target_os = "macos"))))]
+mod aarch64 {
+    use super::TimeSource;
+    use core::arch::asm;
+
+    pub struct Aarch64;
+
+    impl TimeSource for Aarch64 {
+        fn now() -> u64 {
+            let value: u64;
+            unsafe { asm!("mrs {}, cntvct_el0", out(reg) value, options(nomem, nostack)) };
+            value
+        }
+
+        fn known_frequency_hz() -> Option<f64> {
+            let freq: u64;
+            unsafe { asm!("mrs {}, cntfrq_el0", out(reg) freq, options(nomem, nostack)) };
+            if freq > 0 { Some(freq as f64) } else { None }
+        }
+    }
+}
+
+#[cfg(all(target_arch = "x86_64", not(any(target_os = "windows", target_os = "macos"))))]
+pub use x86::X86 as PlatformTimeSource;
+#[cfg(all(target_arch = "x86_64", not(any(target_os = "windows", target_os = "macos"))))]
+mod x86 {
+    use super::TimeSource;
+    use core::arch::x86_64::{_rdtsc, __cpuid};
+
+    pub struct X86;
+
+    impl TimeSource for X86 {
+        fn now() -> u64 {
+            unsafe { _rdtsc() }
+        }
+
+        fn known_frequency_hz() -> Option<f64> {
+            // Frequency read off CPUID is only trustworthy if the TSC ticks
+            // at a constant rate regardless of P-state changes; otherwise
+            // report none and leave CPUInfo with only its calibration loop,
+            // which at least reflects the frequency at the moment it ran.
+            if !Self::invariant() {
+                return None;
+            }
+
+            let max_leaf = __cpuid(0x0).eax;
+
+            // Leaf 0x15: TSC/core-crystal-clock ratio (EBX/EAX) times the
+            // crystal frequency (ECX).
+            if max_leaf >= 0x15 {
+                let leaf15 = __cpuid(0x15);
+                if leaf15.eax != 0 && leaf15.ebx != 0 {
+                    let crystal_hz = if leaf15.ecx != 0 {
+                        leaf15.ecx as f64
+                    } else {
+                        // Crystal frequency not enumerated by this CPU
+                        // (common on earlier parts exposing leaf 0x15);
+                        // 24 MHz is the documented value for those.
+                        24_000_000.0
+                    };
+                    return Some(crystal_hz * leaf15.ebx as f64 / leaf15.eax as f64);
+                }
+            }
+
+            // Leaf 0x16: nominal core base frequency, directly in MHz.
+            if max_leaf >= 0x16 {
+                let leaf16 = __cpuid(0x16);
+                if leaf16.eax != 0 {
+                    return Some(leaf16.eax as f64 * 1_000_000.0);
+                }
+            }
+
+            None
+        }
+
+        fn invariant() -> bool {
+            // CPUID leaf 0x80000007, EDX bit 8: invariant TSC. Requires
+            // checking the max extended leaf first, since leaf 0x80000007
+            // itself is undefined below it.
+            let max_extended_leaf = __cpuid(0x8000_0000).eax;
+            if max_extended_leaf < 0x8000_0007 {
+                return false;
+            }
+            __cpuid(0x8000_0007).edx & (1 << 8) != 0
+        }
+    }
+}
@@ -0,0 +1,67 @@
+//! Opt-in background "upkeep" thread for near-zero-cost coarse timestamps.
+//!
+//! `CPUInfo::get_time_stamp` costs tens of cycles even on the fast RDTSC
+//! path, which can dominate extremely small regions sampled millions of
+//! times. `Upkeep` instead refreshes a shared atomic from a background
+//! thread at a configurable interval; `CycleInstant::recent` then just does a
+//! single relaxed load, trading resolution (bounded by the upkeep interval)
+//! for call overhead close to zero.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::CPUInfo;
+
+static UPKEEP_TIMESTAMP: AtomicU64 = AtomicU64::new(0);
+
+/// Handle to a running upkeep thread. Stops and joins the thread on `Drop`.
+pub struct Upkeep {
+    running: Arc<AtomicBool>,
+    interval: Duration,
+    thread: Option<JoinHandle<()>>
+}
+
+impl Upkeep {
+    /// Start a background thread that refreshes the shared counter every
+    /// `interval`. A smaller interval tightens `CycleInstant::recent`'s
+    /// worst-case error at the cost of more background wakeups; 10-100us is
+    /// a reasonable starting point.
+    pub fn start(interval: Duration) -> Upkeep {
+        let running = Arc::new(AtomicBool::new(true));
+        let thread_running = running.clone();
+        UPKEEP_TIMESTAMP.store(CPUInfo::get_time_stamp(), Ordering::Relaxed);
+
+        let thread = thread::spawn(move || {
+            while thread_running.load(Ordering::Relaxed) {
+                UPKEEP_TIMESTAMP.store(CPUInfo::get_time_stamp(), Ordering::Relaxed);
+                thread::sleep(interval);
+            }
+        });
+
+        Upkeep { running, interval, thread: Some(thread) }
+    }
+
+    /// Worst-case staleness of `CycleInstant::recent`: a reader can observe a
+    /// value up to one full upkeep interval old.
+    pub fn max_error(&self) -> Duration {
+        self.interval
+    }
+}
+
+impl Drop for Upkeep {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Load the most recent timestamp written by a running `Upkeep` thread with a
+/// single relaxed read -- no serialization, no syscall. `0` if no `Upkeep`
+/// has been started yet.
+pub fn recent_time_stamp() -> u64 {
+    UPKEEP_TIMESTAMP.load(Ordering::Relaxed)
+}